@@ -1,15 +1,14 @@
 
 use cgmath::{self, InnerSpace, Vector3};
 use fft;
-use math::{integration, Real};
-use ndarray::{Array2, ArrayView2, ArrayViewMut2, Axis};
+use math::{integration, spectral, Real};
+use math::random_field::gaussian_field_from_spectrum;
+use ndarray::{Array2, ArrayView2, ArrayViewMut2};
 use num::Zero;
 use num::complex::Complex;
-use rand;
-use rand::distributions::normal;
+use rand::Rng;
 
 use std::f32::consts::PI;
-use std::sync::Arc;
 
 fn dispersion_peak<T: Real>(gravity: T, wind_speed: T, fetch: T) -> T {
     // Note: pow(x, 1/3) is missing in [Horvath2015]
@@ -84,55 +83,66 @@ pub struct Parameters<T> {
     pub fetch: T,
     pub swell: T,
     pub domain_size: T,
+    pub foam_scale: T, // scales the Jacobian-folding foam/whitecap estimate
 }
 
-pub fn build_height_spectrum<S, T>(
+/// Build the height spectrum and the per-texel angular frequency `omega`.
+///
+/// `rng` drives the random amplitude/phase of every mode, so the same seed
+/// reproduces the same spectrum across runs and machines (useful for
+/// regression tests and deterministic initial conditions).
+pub fn build_height_spectrum<S, T, R>(
     parameters: &Parameters<T>,
     spectrum: &S,
-    resolution: usize) -> (Array2<Complex<T>>, Array2<T>)
+    resolution: usize,
+    rng: &mut R) -> (Array2<Complex<T>>, Array2<T>)
 where
     S: Spectrum<T>,
-    T: Real
+    T: Real,
+    R: Rng,
 {
+    let height_spectrum = gaussian_field_from_spectrum(
+        resolution,
+        parameters.domain_size,
+        |k| spectrum_power(parameters, spectrum, k),
+        rng,
+    );
+
     let pi = T::new(PI);
-    let mut height_spectrum = Array2::from_elem((resolution, resolution), Complex::new(T::zero(), T::zero()));
     let mut omega = Array2::zeros((resolution, resolution));
 
     par_azip!(
         index (j, i),
-        mut height_spectrum,
         mut omega,
     in {
         let x = T::new(2 * i as isize - resolution as isize - 1);
         let y = T::new(2 * j as isize - resolution as isize - 1);
 
-        let sample = {
-            let k = cgmath::vec2(
-                pi * x / parameters.domain_size,
-                pi * y / parameters.domain_size,
-            );
-            sample_spectrum(parameters, spectrum, k)
-        };
+        let k = cgmath::vec2(
+            pi * x / parameters.domain_size,
+            pi * y / parameters.domain_size,
+        );
 
-        *height_spectrum = sample.0;
-        *omega = sample.1;
+        *omega = if k.magnitude() < T::default_epsilon() {
+            T::zero()
+        } else {
+            dispersion_capillary(parameters, k.magnitude()).0
+        };
     });
 
     (height_spectrum, omega)
 }
 
-fn sample_spectrum<S, T>(
+/// Power at wavevector `pos`, i.e. the value whose square root scales the
+/// white-noise sample drawn by [`gaussian_field_from_spectrum`].
+fn spectrum_power<S, T>(
     parameters: &Parameters<T>,
     spectrum: &S,
-    pos: cgmath::Vector2<T>) -> (Complex<T>, T)
+    pos: cgmath::Vector2<T>) -> T
 where
     S: Spectrum<T>,
     T: Real
 {
-    if pos.magnitude() < T::default_epsilon() {
-        return (Complex::new(T::zero(), T::zero()), T::zero());
-    }
-
     let theta = (pos.y).atan2(pos.x);
     let grad_k = T::new(2.0 * PI) / parameters.domain_size;
 
@@ -140,12 +150,7 @@ where
     let spreading = directional_spreading(parameters, omega, theta, directional_base_donelan_banner);
     let sample = spectrum.evaluate(omega);
 
-    let normal::StandardNormal(z) = rand::random();
-    let phase = T::new(2.0 * PI) * rand::random::<T>();
-
-    let amplitude = T::new(z as f32) * (T::new(2.0) * spreading * sample * grad_k.powi(2) * grad_omega / pos.magnitude()).sqrt();
-
-    (Complex::new(phase.cos() * amplitude, phase.sin() * amplitude), omega)
+    T::new(2.0) * spreading * sample * grad_k.powi(2) * grad_omega / pos.magnitude()
 }
 
 
@@ -226,6 +231,19 @@ pub struct Ocean<T> {
     displacement_x: Array2<Complex<T>>,
     displacement_y: Array2<Complex<T>>,
     displacement_z: Array2<Complex<T>>,
+    // Horizontal-gradient spectra of the x/z displacement, used to build the
+    // folding Jacobian that drives the foam/whitecap output map.
+    gradient_dx_dx: Array2<Complex<T>>,
+    gradient_dx_dz: Array2<Complex<T>>,
+    gradient_dz_dx: Array2<Complex<T>>,
+    gradient_dz_dz: Array2<Complex<T>>,
+    // Spatial-domain transforms of the above, used to build the folding
+    // Jacobian; preallocated for the same reason as the fields above instead
+    // of being allocated fresh every `propagate()` call.
+    fold_dx_dx: Array2<T>,
+    fold_dx_dz: Array2<T>,
+    fold_dz_dx: Array2<T>,
+    fold_dz_dz: Array2<T>,
 }
 
 impl<T> Ocean<T> where T: Real + fft::FFTnum {
@@ -237,6 +255,14 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
             displacement_x: Self::new_map(resolution),
             displacement_y: Self::new_map(resolution),
             displacement_z: Self::new_map(resolution),
+            gradient_dx_dx: Self::new_map(resolution),
+            gradient_dx_dz: Self::new_map(resolution),
+            gradient_dz_dx: Self::new_map(resolution),
+            gradient_dz_dz: Self::new_map(resolution),
+            fold_dx_dx: Self::new_real_map(resolution),
+            fold_dx_dz: Self::new_real_map(resolution),
+            fold_dz_dx: Self::new_real_map(resolution),
+            fold_dz_dz: Self::new_real_map(resolution),
         }
     }
 
@@ -244,17 +270,26 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
         Array2::from_elem((resolution, resolution), Complex::new(T::zero(), T::zero()))
     }
 
+    fn new_real_map(resolution: usize) -> Array2<T> {
+        Array2::from_elem((resolution, resolution), T::zero())
+    }
+
     pub fn new_displacement(&self) -> Array2<Vector3<T>> {
         Array2::from_elem((self.resolution, self.resolution), Vector3::zero())
     }
 
+    pub fn new_foam(&self) -> Array2<T> {
+        Array2::from_elem((self.resolution, self.resolution), T::zero())
+    }
+
     pub fn propagate(
         &mut self,
         time: T,
         parameters: &Parameters<T>,
         samples: ArrayView2<Complex<T>>,
         omega: ArrayView2<T>,
-        mut displacement: ArrayViewMut2<Vector3<T>>)
+        mut displacement: ArrayViewMut2<Vector3<T>>,
+        mut foam: ArrayViewMut2<T>)
     {
         let resolution = self.resolution;
         let pi = T::new(PI);
@@ -294,9 +329,36 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
             *dz = Complex::new(T::zero(), -k_normalized.im) * sample;
         });
 
+        // horizontal-gradient step: multiply the dx/dz spectra by i*k_x and
+        // i*k_z to get the spatial derivatives needed for the folding
+        // Jacobian below, before anything is transformed to the spatial domain.
+        par_azip!(
+            index (j, i),
+            dx (&self.displacement_x),
+            dz (&self.displacement_z),
+            ref gxx (&mut self.gradient_dx_dx),
+            ref gxz (&mut self.gradient_dx_dz),
+            ref gzx (&mut self.gradient_dz_dx),
+            ref gzz (&mut self.gradient_dz_dz),
+        in {
+            let x = T::new(2 * i as isize - resolution as isize - 1);
+            let y = T::new(2 * j as isize - resolution as isize - 1);
+
+            let k_x = pi * x / parameters.domain_size;
+            let k_z = pi * y / parameters.domain_size;
+
+            let i_kx = Complex::new(T::zero(), k_x);
+            let i_kz = Complex::new(T::zero(), k_z);
+
+            *gxx = i_kx * dx;
+            *gxz = i_kz * dx;
+            *gzx = i_kx * dz;
+            *gzz = i_kz * dz;
+        });
+
         let plan = self.fft_plan.plan_fft(self.resolution);
 
-        Self::spectral_to_spatial(&plan, self.displacement_x.view_mut(), self.fft_buffer.view_mut());
+        spectral::transform_2d(&plan, self.displacement_x.view_mut(), self.fft_buffer.view_mut());
         // correction step
         par_azip!(
             index (j, i),
@@ -310,7 +372,7 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
             }
         });
 
-        Self::spectral_to_spatial(&plan, self.displacement_y.view_mut(), self.fft_buffer.view_mut());
+        spectral::transform_2d(&plan, self.displacement_y.view_mut(), self.fft_buffer.view_mut());
         // correction step
         par_azip!(
             index (j, i),
@@ -324,7 +386,7 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
             }
         });
 
-        Self::spectral_to_spatial(&plan, self.displacement_z.view_mut(), self.fft_buffer.view_mut());
+        spectral::transform_2d(&plan, self.displacement_z.view_mut(), self.fft_buffer.view_mut());
         // correction step
         par_azip!(
             index (j, i),
@@ -337,25 +399,37 @@ impl<T> Ocean<T> where T: Real + fft::FFTnum {
                 dst.z = src.re;
             }
         });
-    }
 
-    // Transform a spatial 2d field into a spatial field
-    // Output is stored in self.fft_buffer
-    fn spectral_to_spatial(plan: &Arc<fft::FFT<T>>, mut input: ArrayViewMut2<Complex<T>>, mut output: ArrayViewMut2<Complex<T>>) {
-        par_azip!(
-            mut src (input.axis_iter_mut(Axis(0)))
-            mut dst (output.axis_iter_mut(Axis(0)))
-        in {
-            plan.process(src.as_slice_mut().unwrap(), dst.as_slice_mut().unwrap());
+        // Jacobian folding step: transform the four horizontal-gradient
+        // fields, applying the same checkerboard sign correction as the
+        // displacement maps above, then combine them into the folding
+        // determinant and the foam output.
+        spectral::transform_2d(&plan, self.gradient_dx_dx.view_mut(), self.fft_buffer.view_mut());
+        par_azip!(index (j, i), src (&self.fft_buffer), ref dst (&mut self.fold_dx_dx) in {
+            *dst = if (j+i) % 2 == 0 { -src.re } else { src.re };
         });
 
-        input.assign(&output.t());
+        spectral::transform_2d(&plan, self.gradient_dx_dz.view_mut(), self.fft_buffer.view_mut());
+        par_azip!(index (j, i), src (&self.fft_buffer), ref dst (&mut self.fold_dx_dz) in {
+            *dst = if (j+i) % 2 == 0 { -src.re } else { src.re };
+        });
+
+        spectral::transform_2d(&plan, self.gradient_dz_dx.view_mut(), self.fft_buffer.view_mut());
+        par_azip!(index (j, i), src (&self.fft_buffer), ref dst (&mut self.fold_dz_dx) in {
+            *dst = if (j+i) % 2 == 0 { -src.re } else { src.re };
+        });
+
+        spectral::transform_2d(&plan, self.gradient_dz_dz.view_mut(), self.fft_buffer.view_mut());
+        par_azip!(index (j, i), src (&self.fft_buffer), ref dst (&mut self.fold_dz_dz) in {
+            *dst = if (j+i) % 2 == 0 { -src.re } else { src.re };
+        });
 
         par_azip!(
-            mut src (input.axis_iter_mut(Axis(0)))
-            mut dst (output.axis_iter_mut(Axis(0)))
+            xx (&self.fold_dx_dx), xz (&self.fold_dx_dz), zx (&self.fold_dz_dx), zz (&self.fold_dz_dz),
+            ref out (&mut foam),
         in {
-            plan.process(src.as_slice_mut().unwrap(), dst.as_slice_mut().unwrap());
+            let jacobian = (T::one() + xx) * (T::one() + zz) - xz * zx;
+            *out = (T::one() - jacobian).max(T::zero()) * parameters.foam_scale;
         });
     }
 }
@@ -9,6 +9,8 @@ extern crate generic_array;
 extern crate rayon;
 extern crate typenum;
 extern crate cgmath;
+extern crate fft;
+extern crate rand;
 
 extern crate sprs;
 
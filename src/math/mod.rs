@@ -0,0 +1,3 @@
+
+pub mod random_field;
+pub mod spectral;
@@ -0,0 +1,184 @@
+
+//! Spectral differential operators on 2D periodic grids.
+
+use fft;
+use math::Real;
+use ndarray::{Array2, ArrayView2, ArrayViewMut2, Axis};
+use num::complex::Complex;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+fn wavenumber<T: Real>(index: usize, resolution: usize, domain_size: T) -> T {
+    let pi = T::new(PI);
+    let x = T::new(2 * index as isize - resolution as isize);
+    pi * x / domain_size
+}
+
+/// 2D FFT of `input`, which is overwritten as scratch space. Shared by
+/// both the forward and the inverse transform;
+/// which one is performed depends on whether `plan` was built as forward
+/// or inverse. The result ends up in `output`, transposed relative to
+/// `input`'s (j, i) layout; `to_spectral`/`to_spatial` below correct for
+/// that so callers can index spectra the same way as their spatial fields.
+pub(crate) fn transform_2d<T>(plan: &Arc<fft::FFT<T>>, mut input: ArrayViewMut2<Complex<T>>, mut output: ArrayViewMut2<Complex<T>>)
+where T: Real + fft::FFTnum
+{
+    par_azip!(
+        mut src (input.axis_iter_mut(Axis(0)))
+        mut dst (output.axis_iter_mut(Axis(0)))
+    in {
+        plan.process(src.as_slice_mut().unwrap(), dst.as_slice_mut().unwrap());
+    });
+
+    input.assign(&output.t());
+
+    par_azip!(
+        mut src (input.axis_iter_mut(Axis(0)))
+        mut dst (output.axis_iter_mut(Axis(0)))
+    in {
+        plan.process(src.as_slice_mut().unwrap(), dst.as_slice_mut().unwrap());
+    });
+}
+
+fn to_spectral<T>(field: ArrayView2<T>) -> Array2<Complex<T>>
+where T: Real + fft::FFTnum
+{
+    let resolution = field.dim().0;
+    // Checkerboard-modulate before the forward transform so bin `index`
+    // lands at the same centered wavenumber `wavenumber(index, ...)`
+    // already assumes, matching the sign correction `to_spatial` applies
+    // after the inverse transform.
+    let mut input = Array2::from_shape_fn((resolution, resolution), |(j, i)| {
+        let value = if (j + i) % 2 == 0 { -field[(j, i)] } else { field[(j, i)] };
+        Complex::new(value, T::zero())
+    });
+    let mut buffer = Array2::from_elem((resolution, resolution), Complex::new(T::zero(), T::zero()));
+
+    let plan = fft::FFTplanner::new(false).plan_fft(resolution);
+    transform_2d(&plan, input.view_mut(), buffer.view_mut());
+
+    // transform_2d() leaves its result transposed relative to (j, i); undo
+    // that so spectrum[(j, i)] lines up with wavenumber(i)/wavenumber(j).
+    buffer.t().to_owned()
+}
+
+fn to_spatial<T>(spectrum: Array2<Complex<T>>) -> Array2<T>
+where T: Real + fft::FFTnum
+{
+    let resolution = spectrum.dim().0;
+    // Undo to_spectral's realigning transpose before feeding transform_2d,
+    // which expects/produces the transposed layout.
+    let mut input = spectrum.t().to_owned();
+    let mut buffer = Array2::from_elem((resolution, resolution), Complex::new(T::zero(), T::zero()));
+
+    let plan = fft::FFTplanner::new(true).plan_fft(resolution);
+    transform_2d(&plan, input.view_mut(), buffer.view_mut());
+
+    // rustfft's forward/inverse pair is unnormalized, so a round trip through
+    // both scales the field by resolution^2; undo that here, alongside the
+    // same checkerboard sign correction the ocean code applies.
+    let normalization = T::new((resolution * resolution) as f32);
+    Array2::from_shape_fn((resolution, resolution), |(j, i)| {
+        let value = if (j + i) % 2 == 0 { -buffer[(j, i)].re } else { buffer[(j, i)].re };
+        value / normalization
+    })
+}
+
+fn multiply_i_k<T: Real>(spectrum: &mut Array2<Complex<T>>, resolution: usize, domain_size: T, axis_wavenumber: fn(usize, usize, usize, T) -> T) {
+    par_azip!(index (j, i), mut spectrum in {
+        let k = axis_wavenumber(j, i, resolution, domain_size);
+        let sample = *spectrum;
+        *spectrum = Complex::new(-k * sample.im, k * sample.re);
+    });
+}
+
+fn k_x<T: Real>(_j: usize, i: usize, resolution: usize, domain_size: T) -> T {
+    wavenumber(i, resolution, domain_size)
+}
+
+fn k_y<T: Real>(j: usize, _i: usize, resolution: usize, domain_size: T) -> T {
+    wavenumber(j, resolution, domain_size)
+}
+
+/// Spectrally-accurate gradient `(df/dx, df/dy)` of a real field over a
+/// square periodic domain of side length `domain_size`.
+pub fn gradient<T>(field: ArrayView2<T>, domain_size: T) -> (Array2<T>, Array2<T>)
+where T: Real + fft::FFTnum
+{
+    let resolution = field.dim().0;
+    let spectrum = to_spectral(field);
+
+    let mut spectrum_x = spectrum.clone();
+    let mut spectrum_y = spectrum;
+
+    multiply_i_k(&mut spectrum_x, resolution, domain_size, k_x);
+    multiply_i_k(&mut spectrum_y, resolution, domain_size, k_y);
+
+    (to_spatial(spectrum_x), to_spatial(spectrum_y))
+}
+
+/// Spectral divergence `div(field_x, field_y)` of a vector field.
+pub fn divergence<T>(field_x: ArrayView2<T>, field_y: ArrayView2<T>, domain_size: T) -> Array2<T>
+where T: Real + fft::FFTnum
+{
+    let resolution = field_x.dim().0;
+
+    let mut spectrum_x = to_spectral(field_x);
+    let mut spectrum_y = to_spectral(field_y);
+
+    multiply_i_k(&mut spectrum_x, resolution, domain_size, k_x);
+    multiply_i_k(&mut spectrum_y, resolution, domain_size, k_y);
+
+    par_azip!(index (j, i), mut spectrum_x, src_y (&spectrum_y) in {
+        *spectrum_x = *spectrum_x + src_y;
+    });
+
+    to_spatial(spectrum_x)
+}
+
+/// Spectral Laplacian `d^2f/dx^2 + d^2f/dy^2` of a real field.
+pub fn laplacian<T>(field: ArrayView2<T>, domain_size: T) -> Array2<T>
+where T: Real + fft::FFTnum
+{
+    let resolution = field.dim().0;
+    let mut spectrum = to_spectral(field);
+
+    par_azip!(index (j, i), mut spectrum in {
+        let k_x = wavenumber(i, resolution, domain_size);
+        let k_y = wavenumber(j, resolution, domain_size);
+
+        *spectrum = *spectrum * -(k_x * k_x + k_y * k_y);
+    });
+
+    to_spatial(spectrum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn gradient_of_single_mode_matches_closed_form() {
+        let resolution = 8;
+        let domain_size: f32 = 10.0;
+
+        // A field that varies only along x: d/dy must vanish everywhere,
+        // and d/dx must match the closed-form derivative of cos.
+        let field = Array2::from_shape_fn((resolution, resolution), |(_, i)| {
+            (2.0 * PI * i as f32 / resolution as f32).cos()
+        });
+
+        let (d_dx, d_dy) = gradient(field.view(), domain_size);
+        let k = 2.0 * PI / domain_size;
+
+        for j in 0..resolution {
+            for i in 0..resolution {
+                assert!(d_dy[(j, i)].abs() < 1e-4);
+
+                let expected = -k * (2.0 * PI * i as f32 / resolution as f32).sin();
+                assert!((d_dx[(j, i)] - expected).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+
+//! Generic explicit time-stepping for grid/particle PDE solvers.
+
+use math::Real;
+use ndarray::Array2;
+use std::ops::{Add, Mul};
+
+/// A state advanced by the integrators below: clonable, and closed under
+/// addition and scaling by `T`, the way `ndarray::Array2<T>` already is.
+pub trait State<T>: Clone + Add<Output = Self> + Mul<T, Output = Self> {}
+impl<T, S> State<T> for S where S: Clone + Add<Output = S> + Mul<T, Output = S> {}
+
+/// Forward Euler step: `state + dt * rhs(state, t)`.
+pub fn euler<S, T, F>(state: &S, t: T, dt: T, rhs: F) -> S
+where
+    T: Real,
+    S: State<T>,
+    F: Fn(&S, T) -> S,
+{
+    state.clone() + rhs(state, t) * dt
+}
+
+/// Classical 4th-order Runge-Kutta step.
+pub fn rk4<S, T, F>(state: &S, t: T, dt: T, rhs: F) -> S
+where
+    T: Real,
+    S: State<T>,
+    F: Fn(&S, T) -> S,
+{
+    let half = T::new(0.5);
+    let two = T::new(2.0);
+    let sixth = T::new(1.0 / 6.0);
+
+    let k1 = rhs(state, t);
+    let k2 = rhs(&(state.clone() + k1.clone() * (dt * half)), t + dt * half);
+    let k3 = rhs(&(state.clone() + k2.clone() * (dt * half)), t + dt * half);
+    let k4 = rhs(&(state.clone() + k3.clone() * dt), t + dt);
+
+    state.clone() + (k1 + k2 * two + k3 * two + k4) * (dt * sixth)
+}
+
+/// In-place RK4 stepper for `Array2<T>` state that reuses its `k1..k4` and
+/// `tmp` buffers across steps instead of allocating them every call.
+pub struct Rk4Scratch<T> {
+    k1: Array2<T>,
+    k2: Array2<T>,
+    k3: Array2<T>,
+    k4: Array2<T>,
+    tmp: Array2<T>,
+}
+
+impl<T: Real> Rk4Scratch<T> {
+    pub fn new(shape: (usize, usize)) -> Self {
+        let zeros = || Array2::from_elem(shape, T::zero());
+        Rk4Scratch {
+            k1: zeros(),
+            k2: zeros(),
+            k3: zeros(),
+            k4: zeros(),
+            tmp: zeros(),
+        }
+    }
+
+    /// Advance `state` by `dt` in place. `rhs` writes its result into the
+    /// scratch buffer handed to it instead of returning a new array.
+    pub fn step<F>(&mut self, state: &mut Array2<T>, t: T, dt: T, rhs: F)
+    where
+        F: Fn(&Array2<T>, T, &mut Array2<T>),
+    {
+        let half = T::new(0.5);
+        let two = T::new(2.0);
+        let sixth = T::new(1.0 / 6.0);
+
+        rhs(state, t, &mut self.k1);
+
+        self.tmp.assign(state);
+        self.tmp.scaled_add(dt * half, &self.k1);
+        rhs(&self.tmp, t + dt * half, &mut self.k2);
+
+        self.tmp.assign(state);
+        self.tmp.scaled_add(dt * half, &self.k2);
+        rhs(&self.tmp, t + dt * half, &mut self.k3);
+
+        self.tmp.assign(state);
+        self.tmp.scaled_add(dt, &self.k3);
+        rhs(&self.tmp, t + dt, &mut self.k4);
+
+        state.scaled_add(dt * sixth, &self.k1);
+        state.scaled_add(dt * sixth * two, &self.k2);
+        state.scaled_add(dt * sixth * two, &self.k3);
+        state.scaled_add(dt * sixth, &self.k4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rk4_matches_exponential_decay_closed_form() {
+        let rate = 0.5f32;
+        let rhs = |y: &f32, _t: f32| -rate * y;
+
+        let dt = 0.01f32;
+        let steps = 100;
+        let mut y = 1.0f32;
+        for i in 0..steps {
+            y = rk4(&y, i as f32 * dt, dt, rhs);
+        }
+
+        let expected = (-rate * steps as f32 * dt).exp();
+        assert!((y - expected).abs() < 1e-5);
+    }
+}
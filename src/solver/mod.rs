@@ -0,0 +1,3 @@
+
+pub mod integration;
+pub mod shallow_water;
@@ -0,0 +1,167 @@
+
+//! Depth-averaged (conservative) shallow-water equations on a periodic grid.
+
+use fft;
+use math::{spectral, Real};
+use ndarray::Array2;
+use solver::integration;
+
+use std::ops::{Add, Mul};
+
+pub struct Parameters<T> {
+    pub gravity: T,     // [m/s^2]
+    pub domain_size: T, // side length of the (periodic, square) domain
+}
+
+/// Depth-averaged state: water depth `h` and depth-integrated momentum
+/// `(hu, hv)`, in the conservative shallow-water formulation.
+#[derive(Clone)]
+pub struct State<T> {
+    pub h: Array2<T>,
+    pub hu: Array2<T>,
+    pub hv: Array2<T>,
+}
+
+impl<T: Real> Add for State<T> {
+    type Output = State<T>;
+
+    fn add(self, rhs: State<T>) -> State<T> {
+        State {
+            h: self.h + rhs.h,
+            hu: self.hu + rhs.hu,
+            hv: self.hv + rhs.hv,
+        }
+    }
+}
+
+impl<T: Real> Mul<T> for State<T> {
+    type Output = State<T>;
+
+    fn mul(self, scale: T) -> State<T> {
+        State {
+            h: self.h * scale,
+            hu: self.hu * scale,
+            hv: self.hv * scale,
+        }
+    }
+}
+
+pub struct ShallowWater<T> {
+    pub state: State<T>,
+    bathymetry_gradient: (Array2<T>, Array2<T>),
+    parameters: Parameters<T>,
+}
+
+impl<T> ShallowWater<T>
+where
+    T: Real + fft::FFTnum,
+{
+    pub fn new(state: State<T>, bathymetry: Array2<T>, parameters: Parameters<T>) -> Self {
+        let bathymetry_gradient = spectral::gradient(bathymetry.view(), parameters.domain_size);
+
+        ShallowWater {
+            state,
+            bathymetry_gradient,
+            parameters,
+        }
+    }
+
+    /// Advance the solver by `dt` using classical RK4.
+    pub fn step(&mut self, dt: T) {
+        let gravity = self.parameters.gravity;
+        let domain_size = self.parameters.domain_size;
+        let bathymetry_gradient = &self.bathymetry_gradient;
+
+        let rhs = |state: &State<T>, _t: T| -> State<T> {
+            shallow_water_rhs(state, bathymetry_gradient, gravity, domain_size)
+        };
+
+        self.state = integration::rk4(&self.state, T::zero(), dt, rhs);
+    }
+}
+
+/// Right-hand side of the conservative shallow-water equations: negative
+/// divergence of the mass/momentum fluxes, plus the bathymetry source term.
+fn shallow_water_rhs<T>(
+    state: &State<T>,
+    bathymetry_gradient: &(Array2<T>, Array2<T>),
+    gravity: T,
+    domain_size: T,
+) -> State<T>
+where
+    T: Real + fft::FFTnum,
+{
+    let half = T::new(0.5);
+    let shape = state.h.dim();
+
+    let mut flux_hu_x = Array2::from_elem(shape, T::zero());
+    let mut flux_hu_y = Array2::from_elem(shape, T::zero());
+    let mut flux_hv_x = Array2::from_elem(shape, T::zero());
+    let mut flux_hv_y = Array2::from_elem(shape, T::zero());
+
+    // Dry/near-dry cells (h -> 0, or h < 0 from overshoot) would otherwise
+    // divide by zero here; a single NaN would spread to the whole field on
+    // the next FFT, so clamp h away from zero before dividing.
+    let dry_tolerance = T::default_epsilon();
+
+    par_azip!(
+        h (&state.h), hu (&state.hu), hv (&state.hv),
+        ref fhux (&mut flux_hu_x), ref fhuy (&mut flux_hu_y),
+        ref fhvx (&mut flux_hv_x), ref fhvy (&mut flux_hv_y),
+    in {
+        let h_safe = h.max(dry_tolerance);
+        let u = hu / h_safe;
+        let v = hv / h_safe;
+        let pressure = half * gravity * h_safe * h_safe;
+
+        *fhux = hu * u + pressure;
+        *fhuy = hu * v;
+        *fhvx = hv * u;
+        *fhvy = hv * v + pressure;
+    });
+
+    let dh_dt = spectral::divergence(state.hu.view(), state.hv.view(), domain_size) * -T::one();
+
+    let mut dhu_dt = spectral::divergence(flux_hu_x.view(), flux_hu_y.view(), domain_size) * -T::one();
+    par_azip!(mut dhu_dt, h (&state.h), bx (&bathymetry_gradient.0) in {
+        *dhu_dt = *dhu_dt - gravity * h * bx;
+    });
+
+    let mut dhv_dt = spectral::divergence(flux_hv_x.view(), flux_hv_y.view(), domain_size) * -T::one();
+    par_azip!(mut dhv_dt, h (&state.h), by (&bathymetry_gradient.1) in {
+        *dhv_dt = *dhv_dt - gravity * h * by;
+    });
+
+    State { h: dh_dt, hu: dhu_dt, hv: dhv_dt }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lake_at_rest_is_a_steady_state() {
+        let resolution = 8;
+        let domain_size = 10.0f32;
+
+        let bathymetry = Array2::from_elem((resolution, resolution), 0.0f32);
+        let state = State {
+            h: Array2::from_elem((resolution, resolution), 1.0f32),
+            hu: Array2::from_elem((resolution, resolution), 0.0f32),
+            hv: Array2::from_elem((resolution, resolution), 0.0f32),
+        };
+
+        let mut solver = ShallowWater::new(state, bathymetry, Parameters { gravity: 9.81, domain_size });
+        solver.step(0.01);
+
+        for value in solver.state.h.iter() {
+            assert!((value - 1.0).abs() < 1e-3);
+        }
+        for value in solver.state.hu.iter() {
+            assert!(value.abs() < 1e-3);
+        }
+        for value in solver.state.hv.iter() {
+            assert!(value.abs() < 1e-3);
+        }
+    }
+}